@@ -0,0 +1,281 @@
+//! Structured, machine-readable compiler diagnostics.
+use std::fmt::Write;
+
+/// The severity of a single diagnostic.
+#[derive(Copy, Clone, Eq, PartialEq, Debug)]
+pub enum Severity {
+    Error,
+    Warning,
+}
+
+impl Severity {
+    fn as_str(&self) -> &'static str {
+        match *self {
+            Severity::Error => "error",
+            Severity::Warning => "warning",
+        }
+    }
+}
+
+/// A single compiler diagnostic, tied to a location in a source file.
+#[derive(Clone, Debug)]
+pub struct Diagnostic {
+    pub path: String,
+    pub line: usize,
+    pub column: usize,
+    pub severity: Severity,
+
+    /// A stable, machine-readable code (e.g. "mutable-constant") that editor
+    /// tooling can match on without parsing the human-readable message.
+    pub code: &'static str,
+    pub message: String,
+}
+
+/// A collection of diagnostics produced while compiling one or more modules.
+pub struct Diagnostics {
+    entries: Vec<Diagnostic>,
+}
+
+impl Diagnostics {
+    pub fn new() -> Self {
+        Diagnostics { entries: Vec::new() }
+    }
+
+    pub fn iter(&self) -> impl Iterator<Item = &Diagnostic> {
+        self.entries.iter()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.entries.is_empty()
+    }
+
+    pub fn has_errors(&self) -> bool {
+        self.entries.iter().any(|d| d.severity == Severity::Error)
+    }
+
+    fn push(
+        &mut self,
+        severity: Severity,
+        code: &'static str,
+        path: &str,
+        message: String,
+        line: usize,
+        column: usize,
+    ) {
+        self.entries.push(Diagnostic {
+            path: path.to_string(),
+            line: line,
+            column: column,
+            severity: severity,
+            code: code,
+            message: message,
+        });
+    }
+
+    pub fn error(
+        &mut self,
+        path: &str,
+        message: String,
+        line: usize,
+        column: usize,
+    ) {
+        self.push(Severity::Error, "compile-error", path, message, line, column);
+    }
+
+    pub fn mutable_constant_error(
+        &mut self,
+        path: &str,
+        line: usize,
+        column: usize,
+    ) {
+        self.push(
+            Severity::Error,
+            "mutable-constant",
+            path,
+            "constants can not be defined as mutable".to_string(),
+            line,
+            column,
+        );
+    }
+
+    pub fn unknown_raw_instruction_error(
+        &mut self,
+        name: &str,
+        path: &str,
+        line: usize,
+        column: usize,
+    ) {
+        self.push(
+            Severity::Error,
+            "unknown-raw-instruction",
+            path,
+            format!("the raw instruction \"{}\" does not exist", name),
+            line,
+            column,
+        );
+    }
+
+    pub fn module_not_found_error(
+        &mut self,
+        name: &str,
+        path: &str,
+        line: usize,
+        column: usize,
+    ) {
+        self.push(
+            Severity::Error,
+            "module-not-found",
+            path,
+            format!("the module \"{}\" could not be found", name),
+            line,
+            column,
+        );
+    }
+
+    pub fn required_method_with_receiver_error(
+        &mut self,
+        path: &str,
+        line: usize,
+        column: usize,
+    ) {
+        self.push(
+            Severity::Error,
+            "required-method-with-receiver",
+            path,
+            "required methods can not be defined on an explicit receiver"
+                .to_string(),
+            line,
+            column,
+        );
+    }
+
+    pub fn reassign_immutable_local_error(
+        &mut self,
+        name: &str,
+        path: &str,
+        line: usize,
+        column: usize,
+    ) {
+        self.push(
+            Severity::Error,
+            "reassign-immutable-local",
+            path,
+            format!("the local variable \"{}\" is immutable", name),
+            line,
+            column,
+        );
+    }
+
+    pub fn type_conflict_error(
+        &mut self,
+        expected: &str,
+        found: &str,
+        path: &str,
+        line: usize,
+        column: usize,
+    ) {
+        self.push(
+            Severity::Error,
+            "type-conflict",
+            path,
+            format!("expected a value of type {}, found {}", expected, found),
+            line,
+            column,
+        );
+    }
+
+    pub fn reassign_undefined_local_error(
+        &mut self,
+        name: &str,
+        path: &str,
+        line: usize,
+        column: usize,
+    ) {
+        self.push(
+            Severity::Error,
+            "reassign-undefined-local",
+            path,
+            format!("the local variable \"{}\" is undefined", name),
+            line,
+            column,
+        );
+    }
+
+    /// Renders every diagnostic as a human-readable line, one per entry.
+    pub fn to_text(&self) -> String {
+        let mut out = String::new();
+
+        for diagnostic in &self.entries {
+            let _ = writeln!(
+                out,
+                "{}:{}:{}: {}: {}",
+                diagnostic.path,
+                diagnostic.line,
+                diagnostic.column,
+                diagnostic.severity.as_str(),
+                diagnostic.message
+            );
+        }
+
+        out
+    }
+
+    /// Renders every diagnostic as a JSON array, so external tooling (an
+    /// editor, a language server, CI) can consume compiler output without
+    /// parsing a prettified `Debug` dump.
+    pub fn to_json(&self) -> String {
+        let mut out = String::from("[");
+
+        for (index, diagnostic) in self.entries.iter().enumerate() {
+            if index > 0 {
+                out.push(',');
+            }
+
+            out.push('{');
+            write_json_field(&mut out, "path", &diagnostic.path, true);
+            out.push(',');
+            write_json_field(
+                &mut out,
+                "severity",
+                diagnostic.severity.as_str(),
+                false,
+            );
+            out.push(',');
+            write_json_field(&mut out, "code", diagnostic.code, false);
+            out.push(',');
+            write_json_field(&mut out, "message", &diagnostic.message, true);
+            out.push(',');
+            let _ = write!(out, "\"line\":{}", diagnostic.line);
+            out.push(',');
+            let _ = write!(out, "\"column\":{}", diagnostic.column);
+            out.push('}');
+        }
+
+        out.push(']');
+        out
+    }
+}
+
+fn write_json_field(out: &mut String, name: &str, value: &str, escape: bool) {
+    let _ = write!(out, "\"{}\":", name);
+
+    if escape {
+        out.push('"');
+
+        for ch in value.chars() {
+            match ch {
+                '"' => out.push_str("\\\""),
+                '\\' => out.push_str("\\\\"),
+                '\n' => out.push_str("\\n"),
+                ch if (ch as u32) < 0x20 => {
+                    let _ = write!(out, "\\u{:04x}", ch as u32);
+                }
+                _ => out.push(ch),
+            }
+        }
+
+        out.push('"');
+    } else {
+        let _ = write!(out, "\"{}\"", value);
+    }
+}
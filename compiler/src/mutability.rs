@@ -0,0 +1,6 @@
+//! Whether a binding can be reassigned after its initial definition.
+#[derive(Copy, Clone, Eq, PartialEq, Debug)]
+pub enum Mutability {
+    Immutable,
+    Mutable,
+}
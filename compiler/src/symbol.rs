@@ -0,0 +1,44 @@
+//! A single named binding (a local, a global, an argument, ...) tracked by a
+//! `SymbolTable`.
+use std::cell::RefCell;
+use std::rc::Rc;
+
+use mutability::Mutability;
+use types::Type;
+
+pub struct Symbol {
+    name: String,
+    mutability: Mutability,
+
+    /// The type inferred for this binding. Starts out as `Type::Dynamic`
+    /// and is replaced in place (see `Inferer::generalize`) once inference
+    /// has solved a concrete type for it.
+    value_type: RefCell<Type>,
+}
+
+/// A reference counted handle to a `Symbol`, so the same binding can be
+/// shared between the `SymbolTable` that owns it and every expression that
+/// reads or writes it.
+pub type RcSymbol = Rc<Symbol>;
+
+impl Symbol {
+    pub fn new(name: String, value_type: Type, mutability: Mutability) -> Self {
+        Symbol { name, mutability, value_type: RefCell::new(value_type) }
+    }
+
+    pub fn name(&self) -> &str {
+        &self.name
+    }
+
+    pub fn value_type(&self) -> Type {
+        self.value_type.borrow().clone()
+    }
+
+    pub fn set_type(&self, value_type: Type) {
+        *self.value_type.borrow_mut() = value_type;
+    }
+
+    pub fn is_mutable(&self) -> bool {
+        self.mutability == Mutability::Mutable
+    }
+}
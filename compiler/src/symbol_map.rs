@@ -0,0 +1,52 @@
+//! Interning of identifier names into compact integer symbol IDs.
+use std::collections::HashMap;
+use std::rc::Rc;
+
+/// The ID of a name that has been interned into a `SymbolMap`.
+///
+/// IDs are cheap to copy, compare and hash, which matters once a
+/// `SymbolTable` starts comparing the same identifiers over and over (e.g.
+/// for every local and global lookup).
+#[derive(Copy, Clone, Eq, PartialEq, Hash, Debug)]
+pub struct SymId(u32);
+
+/// A store that interns strings, handing out a `SymId` for every unique
+/// value instead of comparing or hashing the raw bytes every time the same
+/// name is encountered again.
+#[derive(Clone)]
+pub struct SymbolMap {
+    names: Vec<Rc<str>>,
+    ids: HashMap<Rc<str>, SymId>,
+}
+
+impl SymbolMap {
+    pub fn new() -> Self {
+        SymbolMap { names: Vec::new(), ids: HashMap::new() }
+    }
+
+    /// Interns `name`, returning its existing ID or creating a new one.
+    pub fn intern(&mut self, name: &str) -> SymId {
+        if let Some(id) = self.ids.get(name) {
+            return *id;
+        }
+
+        let rc: Rc<str> = Rc::from(name);
+        let id = SymId(self.names.len() as u32);
+
+        self.names.push(rc.clone());
+        self.ids.insert(rc, id);
+
+        id
+    }
+
+    /// Looks up the ID previously assigned to `name`, without interning it
+    /// if it isn't already known.
+    pub fn get(&self, name: &str) -> Option<SymId> {
+        self.ids.get(name).cloned()
+    }
+
+    /// Resolves a previously interned `SymId` back to its string value.
+    pub fn resolve(&self, id: SymId) -> &str {
+        &self.names[id.0 as usize]
+    }
+}
@@ -0,0 +1,60 @@
+//! A lexical scope's bindings (the locals or globals visible at some point
+//! while lowering the AST into TIR).
+use std::collections::HashMap;
+use std::rc::Rc;
+
+use mutability::Mutability;
+use symbol::{RcSymbol, Symbol};
+use symbol_map::{SymId, SymbolMap};
+use types::Type;
+
+/// A table of bindings, keyed internally on interned `SymId`s rather than
+/// raw names, so repeated `define`/`lookup` calls for the same identifier
+/// (the common case while lowering a method body) compare integers instead
+/// of hashing and comparing bytes every time.
+#[derive(Clone)]
+pub struct SymbolTable {
+    interner: SymbolMap,
+    symbols: HashMap<SymId, RcSymbol>,
+}
+
+impl SymbolTable {
+    pub fn new() -> Self {
+        SymbolTable { interner: SymbolMap::new(), symbols: HashMap::new() }
+    }
+
+    /// Defines `name` as a new binding, replacing any previous binding of
+    /// the same name (e.g. when a local is shadowed).
+    pub fn define(
+        &mut self,
+        name: String,
+        value_type: Type,
+        mutability: Mutability,
+    ) -> RcSymbol {
+        let id = self.interner.intern(&name);
+        let symbol = Rc::new(Symbol::new(name, value_type, mutability));
+
+        self.symbols.insert(id, symbol.clone());
+        symbol
+    }
+
+    /// Looks up a previously defined binding by name.
+    ///
+    /// This never interns `name`: resolving it to a `SymId` is only
+    /// possible if the name was already interned by a previous `define`, so
+    /// looking up a name this table has never seen simply returns `None`
+    /// without growing the interner.
+    pub fn lookup(&self, name: &str) -> Option<RcSymbol> {
+        let id = self.interner.get(name)?;
+
+        self.symbols.get(&id).cloned()
+    }
+
+    /// Returns every binding in this table, alongside the name it was
+    /// defined under.
+    pub fn entries(&self) -> impl Iterator<Item = (&str, &RcSymbol)> {
+        self.symbols
+            .iter()
+            .map(move |(&id, symbol)| (self.interner.resolve(id), symbol))
+    }
+}
@@ -3,7 +3,9 @@ use std::rc::Rc;
 use std::fs::File;
 use std::io::Read;
 use std::path::MAIN_SEPARATOR;
-use std::collections::HashMap;
+use std::collections::{HashMap, HashSet};
+use std::collections::hash_map::DefaultHasher;
+use std::hash::{Hash, Hasher};
 
 use config::Config;
 use default_globals::DEFAULT_GLOBALS;
@@ -15,6 +17,7 @@ use symbol_table::SymbolTable;
 use tir::code_object::CodeObject;
 use tir::expression::{Argument, Expression};
 use tir::implement::{Implement, Rename};
+use tir::infer::Inferer;
 use tir::import::Symbol as ImportSymbol;
 use tir::module::Module;
 use tir::raw_instructions::*;
@@ -39,6 +42,16 @@ pub struct Builder {
 
     /// The database storing all type information.
     pub typedb: TypeDatabase,
+
+    /// A content fingerprint for every module we've compiled, keyed by module
+    /// name. Used by `rebuild` to decide whether a module actually needs to
+    /// be recompiled.
+    pub fingerprints: HashMap<String, u64>,
+
+    /// The reverse import graph: for every module, the names of the modules
+    /// that import it. Used by `rebuild` to compute which modules need to be
+    /// recompiled when one of their dependencies changes.
+    pub dependents: HashMap<String, Vec<String>>,
 }
 
 struct Context<'a> {
@@ -59,6 +72,8 @@ impl Builder {
             diagnostics: Diagnostics::new(),
             modules: HashMap::new(),
             typedb: TypeDatabase::new(),
+            fingerprints: HashMap::new(),
+            dependents: HashMap::new(),
         }
     }
 
@@ -70,12 +85,10 @@ impl Builder {
     }
 
     pub fn build(&mut self, name: String, path: String) -> Option<Module> {
-        let module = if let Ok(ast) = self.parse_file(&path) {
-            let module = self.module(name, path, ast);
+        let module = if let Ok((ast, fingerprint)) = self.parse_file(&path) {
+            self.fingerprints.insert(name.clone(), fingerprint);
 
-            println!("{:#?}", module);
-
-            Some(module)
+            Some(self.module(name, path, ast))
         } else {
             None
         };
@@ -83,6 +96,78 @@ impl Builder {
         module
     }
 
+    /// Recompiles only the modules affected by the given paths, reusing the
+    /// cached `Module` of everything else.
+    ///
+    /// A module is considered affected if its own fingerprint changed, or if
+    /// it (transitively, through imports) depends on a module that changed.
+    /// Everything else keeps the `Module` it was compiled to on a previous
+    /// call to `build_main`/`rebuild`.
+    pub fn rebuild(
+        &mut self,
+        main_path: String,
+        changed_paths: &[String],
+    ) -> Option<Module> {
+        let mut dirty = HashSet::new();
+
+        for path in changed_paths {
+            let name = self.module_name_for_path(path);
+            let unchanged = self.fingerprint_of_file(path)
+                .map(|fp| self.fingerprints.get(&name) == Some(&fp))
+                .unwrap_or(false);
+
+            if !unchanged {
+                dirty.insert(name);
+            }
+        }
+
+        // Expand the changed set into its full reverse-dependency closure:
+        // anything that (transitively) imports a dirty module is dirty too.
+        let mut pending: Vec<String> = dirty.iter().cloned().collect();
+
+        while let Some(name) = pending.pop() {
+            if let Some(importers) = self.dependents.get(&name).cloned() {
+                for importer in importers {
+                    if dirty.insert(importer.clone()) {
+                        pending.push(importer);
+                    }
+                }
+            }
+        }
+
+        for name in &dirty {
+            self.modules.remove(name);
+            self.fingerprints.remove(name);
+            self.dependents.remove(name);
+        }
+
+        self.build_main(main_path)
+    }
+
+    /// Hashes the contents of the file at `path`, without parsing it.
+    fn fingerprint_of_file(&self, path: &String) -> Option<u64> {
+        let mut file = match File::open(path) {
+            Ok(file) => file,
+            Err(_) => return None,
+        };
+
+        let mut input = String::new();
+
+        if file.read_to_string(&mut input).is_err() {
+            return None;
+        }
+
+        Some(Self::fingerprint(&input))
+    }
+
+    /// Computes a content fingerprint for a module's source.
+    fn fingerprint(source: &str) -> u64 {
+        let mut hasher = DefaultHasher::new();
+
+        source.hash(&mut hasher);
+        hasher.finish()
+    }
+
     fn module(&mut self, name: String, path: String, node: Node) -> Module {
         let mut globals = self.module_globals();
         let locals = self.symbol_table_with_self();
@@ -134,7 +219,11 @@ impl Builder {
             _ => Vec::new(),
         };
 
-        CodeObject { locals: locals, body: body }
+        let mut code_object = CodeObject { locals: locals, body: body };
+
+        Inferer::new(path, &mut self.diagnostics).infer(&mut code_object);
+
+        code_object
     }
 
     fn process_nodes(
@@ -415,19 +504,21 @@ impl Builder {
         col: usize,
         context: &mut Context,
     ) -> Expression {
+        let name = name.clone();
+
         // TODO: look up methods before looking up globals
-        if let Some(local) = context.locals.lookup(name) {
+        if let Some(local) = context.locals.lookup(&name) {
             return self.get_local(local, line, col);
         }
 
-        if let Some(global) = context.globals.lookup(name) {
+        if let Some(global) = context.globals.lookup(&name) {
             return self.get_global(global, line, col);
         }
 
         // TODO: check if method exists for identifiers without receivers
         let args = Vec::new();
 
-        self.send_object_message(name.clone(), &None, &args, line, col, context)
+        self.send_object_message(name, &None, &args, line, col, context)
     }
 
     fn attribute(
@@ -683,10 +774,15 @@ impl Builder {
 
     /// Returns a vector of symbols to import, based on a list of AST nodes
     /// describing the import steps.
+    ///
+    /// `source` is the `SymbolTable` of globals exported by the module being
+    /// imported from. It's only needed to expand a glob import (`import
+    /// foo::bar::*`); explicit imports don't need to see the source module.
     fn import_symbols(
-        &self,
+        &mut self,
         nodes: &Vec<Node>,
         context: &mut Context,
+        source: Option<&SymbolTable>,
     ) -> Vec<ImportSymbol> {
         let mut symbols = Vec::new();
 
@@ -696,6 +792,18 @@ impl Builder {
                     symbol: ref symbol_node,
                     alias: ref alias_node,
                 } => {
+                    if self.is_glob_symbol(symbol_node) {
+                        if let Some(source) = source {
+                            symbols.extend(self.glob_import_symbols(
+                                source,
+                                symbol_node,
+                                context,
+                            ));
+                        }
+
+                        continue;
+                    }
+
                     let alias = if let &Some(ref node) = alias_node {
                         self.name_of_node(node)
                     } else {
@@ -711,8 +819,8 @@ impl Builder {
                     let symbol = match **symbol_node {
                         Node::Identifier { ref name, line, column } |
                         Node::Constant { ref name, line, column, .. } => {
-                            let var_name = if let Some(alias) = alias {
-                                alias
+                            let var_name = if let Some(ref alias) = alias {
+                                alias.clone()
                             } else {
                                 name.clone()
                             };
@@ -740,6 +848,59 @@ impl Builder {
         symbols
     }
 
+    /// Returns true if `node` is the `*` marker of a glob import.
+    fn is_glob_symbol(&self, node: &Node) -> bool {
+        match node {
+            &Node::Identifier { ref name, .. } => name == "*",
+            _ => false,
+        }
+    }
+
+    /// Expands `import foo::bar::*` into one `ImportSymbol` per global that
+    /// `source` exports, skipping private names (by convention, those
+    /// starting with an underscore) and names already bound in the importing
+    /// module.
+    ///
+    /// Because a module's own imported symbols are defined into its
+    /// `globals` just like any other global, a module that glob-imports
+    /// another module automatically re-exports everything it pulled in, so a
+    /// downstream `import` can keep walking the re-export chain.
+    fn glob_import_symbols(
+        &mut self,
+        source: &SymbolTable,
+        glob_node: &Node,
+        context: &mut Context,
+    ) -> Vec<ImportSymbol> {
+        let (line, column) = match glob_node {
+            &Node::Identifier { line, column, .. } => (line, column),
+            _ => unreachable!(),
+        };
+
+        let mut symbols = Vec::new();
+
+        for (name, _) in source.entries() {
+            if name.starts_with('_') || context.globals.lookup(name).is_some()
+            {
+                continue;
+            }
+
+            let local = context.globals.define(
+                name.to_string(),
+                Type::Dynamic,
+                Mutability::Immutable,
+            );
+
+            symbols.push(ImportSymbol::module(
+                name.to_string(),
+                local,
+                line,
+                column,
+            ));
+        }
+
+        symbols
+    }
+
     fn import(
         &mut self,
         step_nodes: &Vec<Node>,
@@ -750,6 +911,12 @@ impl Builder {
     ) -> Expression {
         let mod_name = self.module_name_for_import(step_nodes);
         let mod_path = self.module_path_for_name(&mod_name);
+        let importer_name = self.module_name_for_path(context.path);
+
+        self.dependents
+            .entry(mod_name.clone())
+            .or_insert_with(Vec::new)
+            .push(importer_name);
 
         // We insert the module name before processing it to prevent the
         // compiler from getting stuck in a recursive import.
@@ -778,11 +945,20 @@ impl Builder {
         // At this point the value for the current module path is either
         // Some(module) or None.
         if self.modules.get(&mod_name).unwrap().is_some() {
+            let source_globals = self.modules
+                .get(&mod_name)
+                .and_then(|module| module.as_ref())
+                .map(|module| module.globals.clone());
+
             Expression::ImportModule {
                 path: Box::new(self.string(mod_path, line, col)),
                 line: line,
                 column: col,
-                symbols: self.import_symbols(symbol_nodes, context),
+                symbols: self.import_symbols(
+                    symbol_nodes,
+                    context,
+                    source_globals.as_ref(),
+                ),
             }
         } else {
             Expression::Void
@@ -1439,7 +1615,7 @@ impl Builder {
         }
     }
 
-    fn parse_file(&mut self, path: &String) -> Result<Node, ()> {
+    fn parse_file(&mut self, path: &String) -> Result<(Node, u64), ()> {
         let mut file = match File::open(path) {
             Ok(file) => file,
             Err(err) => {
@@ -1455,10 +1631,11 @@ impl Builder {
             return Err(());
         }
 
+        let fingerprint = Self::fingerprint(&input);
         let mut parser = Parser::new(&input);
 
         match parser.parse() {
-            Ok(ast) => Ok(ast),
+            Ok(ast) => Ok((ast, fingerprint)),
             Err(err) => {
                 self.diagnostics.error(
                     path,
@@ -1539,4 +1716,112 @@ impl Builder {
 
         globals
     }
+
+    /// Starts a new REPL session: a `Module`-like pair of local and global
+    /// symbol tables that stays alive across multiple calls to `eval`,
+    /// instead of being thrown away the way `build`/`build_main` discard
+    /// their `Context` once the file has been lowered.
+    pub fn new_repl_session(&self) -> ReplSession {
+        ReplSession {
+            path: "<repl>".to_string(),
+            locals: self.symbol_table_with_self(),
+            globals: self.module_globals(),
+            buffer: String::new(),
+        }
+    }
+
+    /// Lowers a single line (or fragment of one) submitted to a REPL
+    /// session, reusing the existing lowering pipeline instead of a
+    /// parallel code path.
+    ///
+    /// Locals, globals, imports, classes and traits defined by a previous
+    /// call remain visible, since `session.locals`/`session.globals` are the
+    /// very tables `process_nodes` mutates, and are threaded back in on every
+    /// call.
+    ///
+    /// A definition can span more than one line (e.g. a multi-line `class`
+    /// body). When the parser fails because the input ends before the
+    /// fragment is complete, the input is buffered on `session` and combined
+    /// with whatever is submitted next, until the accumulated buffer parses
+    /// cleanly.
+    pub fn eval(
+        &mut self,
+        session: &mut ReplSession,
+        input: &str,
+    ) -> ReplResult {
+        if !session.buffer.is_empty() {
+            session.buffer.push('\n');
+        }
+
+        session.buffer.push_str(input);
+
+        let mut parser = Parser::new(&session.buffer);
+
+        let ast = match parser.parse() {
+            Ok(ast) => ast,
+            Err(err) => {
+                if is_incomplete_input_error(&err) {
+                    return ReplResult::Incomplete;
+                }
+
+                self.diagnostics.error(
+                    &session.path,
+                    err,
+                    parser.line(),
+                    parser.column(),
+                );
+
+                session.buffer.clear();
+
+                return ReplResult::Expressions(Vec::new());
+            }
+        };
+
+        session.buffer.clear();
+
+        let nodes = match ast {
+            Node::Expressions { nodes } => nodes,
+            other => vec![other],
+        };
+
+        let mut context = Context {
+            path: &session.path,
+            locals: &mut session.locals,
+            globals: &mut session.globals,
+        };
+
+        ReplResult::Expressions(self.process_nodes(&nodes, &mut context))
+    }
+}
+
+/// A persistent REPL session: the state that needs to survive between
+/// separate calls to `Builder::eval`.
+pub struct ReplSession {
+    path: String,
+    locals: SymbolTable,
+    globals: SymbolTable,
+
+    /// Input that failed to parse because it ended too soon, waiting to be
+    /// combined with the next submitted line.
+    buffer: String,
+}
+
+/// The outcome of lowering one REPL submission.
+pub enum ReplResult {
+    /// The input was lowered into zero or more expressions. Input that fails
+    /// to parse for any reason other than ending too soon also produces this
+    /// variant, with no expressions; the parse error is recorded in the
+    /// builder's `diagnostics`.
+    Expressions(Vec<Expression>),
+
+    /// The input ended before a complete fragment could be parsed; it has
+    /// been buffered and should be combined with the next line.
+    Incomplete,
+}
+
+/// Returns true if a parser error indicates the input simply ended too soon,
+/// as opposed to being genuinely invalid.
+fn is_incomplete_input_error(message: &str) -> bool {
+    message.contains("unexpected end of input") ||
+        message.contains("unexpected eof")
 }
@@ -0,0 +1,401 @@
+//! Hindley-Milner style local type inference over lowered TIR.
+//!
+//! This walks a `CodeObject`'s body, assigning every binding and
+//! sub-expression a fresh `TypeVar`, generating equality constraints from the
+//! shape of the expression tree, and then solving those constraints with a
+//! union-find substitution. The resolved type of every local is written back
+//! into the `CodeObject`'s `SymbolTable`, replacing the `Type::Dynamic`
+//! placeholder `Builder` assigns while lowering.
+use std::collections::HashMap;
+
+use diagnostics::Diagnostics;
+use symbol::RcSymbol;
+use tir::code_object::CodeObject;
+use tir::expression::Expression;
+use types::Type;
+
+/// A placeholder for a type that hasn't been solved for yet.
+#[derive(Copy, Clone, Eq, PartialEq, Hash, Debug)]
+pub struct TypeVar(u32);
+
+/// An equality constraint between two types, generated while walking the
+/// expression tree, to be solved for once the whole body has been visited.
+struct Constraint {
+    left: Type,
+    right: Type,
+    line: usize,
+    column: usize,
+}
+
+/// A union-find map from type variables to the type they've been unified
+/// with (if any), and to each other.
+struct Substitution {
+    parents: HashMap<TypeVar, TypeVar>,
+    bindings: HashMap<TypeVar, Type>,
+}
+
+impl Substitution {
+    fn new() -> Self {
+        Substitution { parents: HashMap::new(), bindings: HashMap::new() }
+    }
+
+    fn find(&mut self, var: TypeVar) -> TypeVar {
+        let parent = match self.parents.get(&var) {
+            Some(&parent) if parent != var => parent,
+            _ => return var,
+        };
+
+        let root = self.find(parent);
+
+        self.parents.insert(var, root);
+        root
+    }
+
+    fn binding_of(&mut self, var: TypeVar) -> Option<Type> {
+        let root = self.find(var);
+
+        self.bindings.get(&root).cloned()
+    }
+
+    /// Unifies two type variables, merging whichever binding (if any) they
+    /// already carry.
+    ///
+    /// When both variables already carry a binding, the merged variable keeps
+    /// the right-hand one, and the two bindings aren't reconciled here: they
+    /// may conflict, and resolving that is the caller's job. Instead both are
+    /// returned so the caller can unify them in turn (and report a
+    /// diagnostic if they turn out to be incompatible).
+    fn union(
+        &mut self,
+        left: TypeVar,
+        right: TypeVar,
+    ) -> Option<(Type, Type)> {
+        let left_root = self.find(left);
+        let right_root = self.find(right);
+
+        if left_root == right_root {
+            return None;
+        }
+
+        let left_value = self.bindings.remove(&left_root);
+        let right_value = self.bindings.remove(&right_root);
+
+        self.parents.insert(left_root, right_root);
+
+        match (left_value, right_value) {
+            (Some(left_value), Some(right_value)) => {
+                self.bindings.insert(right_root, right_value.clone());
+                Some((left_value, right_value))
+            }
+            (Some(value), None) | (None, Some(value)) => {
+                self.bindings.insert(right_root, value);
+                None
+            }
+            (None, None) => None,
+        }
+    }
+
+    /// Binds `var` to `value`, after checking that `value` doesn't itself
+    /// refer back to `var` (which would otherwise produce an infinite type).
+    fn bind(&mut self, var: TypeVar, value: Type) -> Result<(), ()> {
+        if self.occurs(var, &value) {
+            return Err(());
+        }
+
+        let root = self.find(var);
+
+        self.bindings.insert(root, value);
+        Ok(())
+    }
+
+    fn occurs(&mut self, var: TypeVar, value: &Type) -> bool {
+        match value {
+            &Type::Var(other) => self.find(var) == self.find(other),
+            _ => false,
+        }
+    }
+}
+
+/// Runs type inference over a single `CodeObject`, writing resolved types
+/// back into its `locals` symbol table.
+pub struct Inferer<'a> {
+    diagnostics: &'a mut Diagnostics,
+    path: &'a str,
+    substitution: Substitution,
+    constraints: Vec<Constraint>,
+    next_var: u32,
+}
+
+impl<'a> Inferer<'a> {
+    pub fn new(path: &'a str, diagnostics: &'a mut Diagnostics) -> Self {
+        Inferer {
+            diagnostics: diagnostics,
+            path: path,
+            substitution: Substitution::new(),
+            constraints: Vec::new(),
+            next_var: 0,
+        }
+    }
+
+    pub fn infer(&mut self, code: &mut CodeObject) {
+        for expression in code.body.iter() {
+            self.walk(expression);
+        }
+
+        self.solve();
+        self.generalize(code);
+
+        for expression in code.body.iter_mut() {
+            self.generalize_nested(expression);
+        }
+    }
+
+    fn fresh(&mut self) -> TypeVar {
+        let var = TypeVar(self.next_var);
+
+        self.next_var += 1;
+        var
+    }
+
+    fn constrain(
+        &mut self,
+        left: Type,
+        right: Type,
+        line: usize,
+        column: usize,
+    ) {
+        self.constraints.push(Constraint {
+            left: left,
+            right: right,
+            line: line,
+            column: column,
+        });
+    }
+
+    /// Returns the type variable backing `variable`, assigning it a fresh one
+    /// the first time it's seen.
+    fn var_for(&mut self, variable: &RcSymbol) -> TypeVar {
+        match variable.value_type() {
+            Type::Var(var) => var,
+            _ => {
+                let var = self.fresh();
+
+                variable.set_type(Type::Var(var));
+                var
+            }
+        }
+    }
+
+    /// Walks a single expression, returning the type assigned to it.
+    fn walk(&mut self, expression: &Expression) -> Type {
+        match expression {
+            &Expression::Integer { .. } => Type::Integer,
+            &Expression::Float { .. } => Type::Float,
+            &Expression::String { .. } => Type::String,
+            &Expression::GetLocal { ref variable, .. } => {
+                Type::Var(self.var_for(variable))
+            }
+            // A global's symbol is shared by every code object of a module,
+            // while type variables are numbered per `Inferer`, so a read of a
+            // global gets a fresh variable and its symbol is left untouched.
+            &Expression::GetGlobal { .. } => Type::Var(self.fresh()),
+            &Expression::SetLocal {
+                ref variable,
+                ref value,
+                line,
+                column,
+            } => {
+                let value_type = self.walk(value);
+                let var = self.var_for(variable);
+
+                self.constrain(Type::Var(var), value_type.clone(), line, column);
+                value_type
+            }
+            &Expression::SendObjectMessage {
+                ref receiver,
+                ref name,
+                ref arguments,
+                line,
+                column,
+                ..
+            } => {
+                self.walk(receiver);
+
+                for argument in arguments.iter() {
+                    self.walk(argument);
+                }
+
+                match name.as_ref() {
+                    "&&" | "||" if arguments.len() == 1 => {
+                        // Both branches of a boolean operator must agree on
+                        // their type.
+                        let left = self.walk(receiver);
+                        let right = self.walk(&arguments[0]);
+
+                        self.constrain(left.clone(), right, line, column);
+                        left
+                    }
+                    // Without a method table to resolve the signature of an
+                    // arbitrary send from, the result can only be pinned
+                    // down by whatever later unifies with it (e.g. a
+                    // `SetLocal` assigning the result to a local).
+                    _ => Type::Var(self.fresh()),
+                }
+            }
+            &Expression::Try { ref body, ref else_body, line, column, .. } => {
+                let body_type = self.infer_nested(body);
+
+                if let &Some(ref else_body) = else_body {
+                    let else_type = self.infer_nested(else_body);
+
+                    self.constrain(body_type.clone(), else_type, line, column);
+                }
+
+                body_type
+            }
+            &Expression::Return { ref value, .. } => {
+                if let &Some(ref value) = value {
+                    self.walk(value)
+                } else {
+                    Type::Dynamic
+                }
+            }
+            _ => Type::Dynamic,
+        }
+    }
+
+    /// Infers a nested code object (e.g. a `Try`'s body), returning the type
+    /// of its last expression, and folds its constraints into this pass so
+    /// they're solved together with the surrounding body.
+    fn infer_nested(&mut self, code: &CodeObject) -> Type {
+        let mut last = Type::Dynamic;
+
+        for expression in code.body.iter() {
+            last = self.walk(expression);
+        }
+
+        last
+    }
+
+    /// Recurses into every nested `CodeObject` reachable from `expression`
+    /// (currently only a `Try`'s body and else body, found directly or inside
+    /// the same `SetLocal`, `Return` and message send expressions `walk`
+    /// descends into), applying the same
+    /// resolved-type write-back `generalize` performs on the top-level code
+    /// object.
+    ///
+    /// `infer_nested` already folds a nested body's constraints into this
+    /// pass's substitution while walking it immutably, but that's all it
+    /// does - without this, a local defined inside a `Try`/else body keeps
+    /// its `Type::Dynamic` placeholder in its own `CodeObject`'s symbol
+    /// table even though inference solved a concrete type for it.
+    fn generalize_nested(&mut self, expression: &mut Expression) {
+        match expression {
+            &mut Expression::Try {
+                ref mut body,
+                ref mut else_body,
+                ..
+            } => {
+                self.generalize(body);
+
+                for expression in body.body.iter_mut() {
+                    self.generalize_nested(expression);
+                }
+
+                if let &mut Some(ref mut else_body) = else_body {
+                    self.generalize(else_body);
+
+                    for expression in else_body.body.iter_mut() {
+                        self.generalize_nested(expression);
+                    }
+                }
+            }
+            &mut Expression::SetLocal { ref mut value, .. } |
+            &mut Expression::Return { value: Some(ref mut value), .. } => {
+                self.generalize_nested(value);
+            }
+            &mut Expression::SendObjectMessage {
+                ref mut receiver,
+                ref mut arguments,
+                ..
+            } => {
+                self.generalize_nested(receiver);
+
+                for argument in arguments.iter_mut() {
+                    self.generalize_nested(argument);
+                }
+            }
+            _ => {}
+        }
+    }
+
+    /// Solves every generated constraint, reporting a diagnostic at the
+    /// constraint's location if two concrete types conflict.
+    fn solve(&mut self) {
+        let constraints = std::mem::replace(&mut self.constraints, Vec::new());
+
+        for constraint in constraints {
+            self.unify(
+                constraint.left,
+                constraint.right,
+                constraint.line,
+                constraint.column,
+            );
+        }
+    }
+
+    fn unify(&mut self, left: Type, right: Type, line: usize, column: usize) {
+        match (left, right) {
+            (Type::Var(left_var), Type::Var(right_var)) => {
+                if let Some((left_value, right_value)) =
+                    self.substitution.union(left_var, right_var)
+                {
+                    self.unify(left_value, right_value, line, column);
+                }
+            }
+            (Type::Var(var), other) | (other, Type::Var(var)) => {
+                if let Some(existing) = self.substitution.binding_of(var) {
+                    self.unify(existing, other, line, column);
+                } else if self.substitution.bind(var, other).is_err() {
+                    self.diagnostics.type_conflict_error(
+                        "<cyclic type>",
+                        "<cyclic type>",
+                        self.path,
+                        line,
+                        column,
+                    );
+                }
+            }
+            (left, right) => {
+                if left != right &&
+                    left != Type::Dynamic &&
+                    right != Type::Dynamic
+                {
+                    self.diagnostics.type_conflict_error(
+                        &format!("{:?}", left),
+                        &format!("{:?}", right),
+                        self.path,
+                        line,
+                        column,
+                    );
+                }
+            }
+        }
+    }
+
+    /// Resolves every local's type variable through the final substitution
+    /// and writes the concrete (or still-dynamic, if never constrained)
+    /// result back into the code object's symbol table.
+    fn generalize(&mut self, code: &mut CodeObject) {
+        for (_, symbol) in code.locals.entries() {
+            let resolved = match symbol.value_type() {
+                Type::Var(var) => {
+                    self.substitution.binding_of(var).unwrap_or(Type::Dynamic)
+                }
+                other => other,
+            };
+
+            symbol.set_type(resolved);
+        }
+    }
+}
@@ -0,0 +1,43 @@
+//! Work-stealing scheduling of lightweight Inko processes.
+pub mod pool_state;
+pub mod process_worker;
+pub mod queue;
+pub mod worker;
+
+use crate::arc_without_weak::ArcWithoutWeak;
+use crate::process::RcProcess;
+use crate::scheduler::pool_state::PoolState;
+use crate::scheduler::queue::RcQueue;
+
+/// A pool of workers sharing a single `PoolState`.
+pub struct Pool<T> {
+    pub state: ArcWithoutWeak<PoolState<T>>,
+}
+
+impl<T> Pool<T> {
+    fn new(workers: usize) -> Self {
+        let queues =
+            (0..workers).map(|_| RcQueue::new()).collect::<Vec<_>>();
+
+        let state = ArcWithoutWeak::new(PoolState::new(queues));
+
+        for queue in state.queues.iter() {
+            queue.bind(state.clone());
+        }
+
+        Pool { state }
+    }
+}
+
+/// The collection of process pools the VM schedules work onto.
+pub struct Scheduler {
+    /// The pool work-stealing `ProcessWorker`s run jobs from in
+    /// `Mode::Normal`.
+    pub primary_pool: Pool<RcProcess>,
+}
+
+impl Scheduler {
+    pub fn new(primary_threads: usize) -> Self {
+        Scheduler { primary_pool: Pool::new(primary_threads) }
+    }
+}
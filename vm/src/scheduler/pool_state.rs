@@ -0,0 +1,213 @@
+//! State shared by every worker in a single scheduler pool.
+use crate::scheduler::process_worker::BroadcastJob;
+use crate::scheduler::queue::RcQueue;
+use std::collections::VecDeque;
+use std::sync::atomic::{AtomicBool, AtomicUsize, Ordering};
+use std::sync::{Condvar, Mutex};
+
+/// State shared between a pool's `ProcessWorker`s: the per-worker queues,
+/// the pool-wide global queue jobs get pushed onto when no worker-local
+/// queue is a good fit, and the synchronisation primitives workers use to
+/// park/wake each other and to coordinate broadcasts.
+pub struct PoolState<T> {
+    /// The queues owned by the individual workers in this pool.
+    pub queues: Vec<RcQueue<T>>,
+
+    /// Jobs that have been pushed onto the pool as a whole, rather than onto
+    /// a specific worker's queue.
+    global: Mutex<VecDeque<T>>,
+
+    /// Used together with `global` to park/wake workers that find no work to
+    /// steal.
+    condvar: Condvar,
+
+    /// Set to `false` to signal every worker in the pool that it should
+    /// terminate.
+    alive: AtomicBool,
+
+    /// The jobs pushed by `PoolState::broadcast`, run exactly once by every
+    /// worker (including parked ones) the next time it checks in.
+    broadcast_jobs: Mutex<Vec<BroadcastJob>>,
+
+    /// Bumped every time a broadcast job is pushed, so workers can tell
+    /// whether they've seen the latest broadcast without re-running old ones.
+    broadcast_generation: AtomicUsize,
+
+    /// The pool's jobs event counter (JEC), bumped every time a job becomes
+    /// available anywhere in the pool (a push onto `global`, or onto any
+    /// worker's local/external queue), so a worker can tell whether anything
+    /// happened between the start of a search round and the point where
+    /// it's about to park.
+    ///
+    /// This exists to close the check-then-park race a worker would
+    /// otherwise hit: checking every queue, finding nothing, and parking,
+    /// all in the window between a producer's check ("is anyone parked?")
+    /// and its push. Comparing the JEC before and after one last search
+    /// round (see `sleep_if_jobs_event_count_is`) makes that race impossible
+    /// to lose: either the round sees the new job, or the JEC has moved and
+    /// the worker knows to loop instead of sleeping.
+    jobs_event_count: AtomicUsize,
+
+    /// The number of workers that have announced themselves as "sleepy" (see
+    /// `begin_sleepy`), i.e. about to park pending one last search round.
+    sleeping: AtomicUsize,
+}
+
+impl<T> PoolState<T> {
+    pub fn new(queues: Vec<RcQueue<T>>) -> Self {
+        PoolState {
+            queues,
+            global: Mutex::new(VecDeque::new()),
+            condvar: Condvar::new(),
+            alive: AtomicBool::new(true),
+            broadcast_jobs: Mutex::new(Vec::new()),
+            broadcast_generation: AtomicUsize::new(0),
+            jobs_event_count: AtomicUsize::new(0),
+            sleeping: AtomicUsize::new(0),
+        }
+    }
+
+    pub fn is_alive(&self) -> bool {
+        self.alive.load(Ordering::Acquire)
+    }
+
+    /// Signals every worker in the pool that it should terminate, waking up
+    /// any that are currently parked.
+    pub fn terminate(&self) {
+        self.alive.store(false, Ordering::Release);
+        self.condvar.notify_all();
+    }
+
+    /// Pushes `job` onto the pool's global queue, waking up a single parked
+    /// worker (if any) to come pick it up.
+    pub fn push_global(&self, job: T) {
+        self.global.lock().unwrap().push_back(job);
+        self.notify_jobs_event();
+        self.condvar.notify_one();
+    }
+
+    pub fn pop_global(&self) -> Option<T> {
+        self.global.lock().unwrap().pop_front()
+    }
+
+    pub fn has_global_jobs(&self) -> bool {
+        !self.global.lock().unwrap().is_empty()
+    }
+
+    /// Parks the calling thread on the pool's condvar for as long as
+    /// `keep_waiting` returns `true`, waking up whenever the pool is woken
+    /// (e.g. by `push_global`, `broadcast`, or `terminate`).
+    pub fn park_while<F>(&self, keep_waiting: F)
+    where
+        F: Fn() -> bool,
+    {
+        let guard = self.global.lock().unwrap();
+
+        let _ = self
+            .condvar
+            .wait_while(guard, |_| self.is_alive() && keep_waiting());
+    }
+
+    /// Runs `job` exactly once on every worker in the pool, including
+    /// workers that are currently parked.
+    ///
+    /// This mirrors rayon-core's `broadcast`: the job is recorded alongside
+    /// a freshly bumped generation counter, and every parked worker is woken
+    /// up so it can notice and run it on its own next check-in.
+    pub fn broadcast(&self, job: BroadcastJob) {
+        self.broadcast_jobs.lock().unwrap().push(job);
+        self.broadcast_generation.fetch_add(1, Ordering::AcqRel);
+
+        // Notifying while holding the lock the park predicates are checked
+        // under means a worker can't check the generation, miss this bump,
+        // and only then start waiting.
+        let _guard = self.global.lock().unwrap();
+
+        self.condvar.notify_all();
+    }
+
+    /// Returns whether a broadcast has been pushed since `since`.
+    pub fn has_broadcast_since(&self, since: usize) -> bool {
+        self.broadcast_generation.load(Ordering::Acquire) > since
+    }
+
+    /// Returns every broadcast job pushed since `since`, along with the
+    /// generation they should be recorded as having seen.
+    pub fn broadcast_jobs_since(
+        &self,
+        since: usize,
+    ) -> (Vec<BroadcastJob>, usize) {
+        let jobs = self.broadcast_jobs.lock().unwrap();
+        let generation = self.broadcast_generation.load(Ordering::Acquire);
+
+        if generation == since {
+            return (Vec::new(), generation);
+        }
+
+        // Every job is pushed before the generation is bumped for it, so the
+        // jobs at `since..generation` are exactly those not yet seen.
+        (jobs[since..generation].to_vec(), generation)
+    }
+
+    /// Returns the pool's current jobs event counter (JEC).
+    pub fn jobs_event_count(&self) -> usize {
+        self.jobs_event_count.load(Ordering::Acquire)
+    }
+
+    /// Bumps the JEC, and wakes up the pool if any worker has announced
+    /// itself as sleepy, so it can re-check before actually parking.
+    ///
+    /// Called from `push_global`, as well as from `RcQueue::push_internal`
+    /// and `RcQueue::push_external`, since a job landing directly on a
+    /// worker's own queue is just as able to wake a sleepy sibling as one
+    /// landing on the global queue.
+    pub fn notify_jobs_event(&self) {
+        self.jobs_event_count.fetch_add(1, Ordering::AcqRel);
+
+        if self.sleeping.load(Ordering::Acquire) > 0 {
+            // Taking the lock `sleep_if_jobs_event_count_is` compares the JEC
+            // under means the notification can't land between that
+            // comparison and the wait.
+            let _guard = self.global.lock().unwrap();
+
+            self.condvar.notify_all();
+        }
+    }
+
+    /// Announces that the calling worker is about to perform its last
+    /// pre-sleep search round.
+    pub fn begin_sleepy(&self) {
+        self.sleeping.fetch_add(1, Ordering::AcqRel);
+    }
+
+    /// Cancels a previous `begin_sleepy` call, used when the worker found
+    /// work during its last search round after all and isn't going to sleep.
+    pub fn end_sleepy(&self) {
+        self.sleeping.fetch_sub(1, Ordering::AcqRel);
+    }
+
+    /// Parks the calling worker, unless the JEC has moved past `since` (i.e.
+    /// a job arrived during the worker's last search round, and it should
+    /// loop instead of sleeping) or a broadcast has been pushed since
+    /// `last_seen_broadcast` (i.e. there's a broadcast job waiting for this
+    /// worker to run, which it would otherwise not notice until something
+    /// else happens to wake the pool).
+    ///
+    /// Either way, the worker is no longer considered sleepy once this
+    /// returns.
+    pub fn sleep_if_jobs_event_count_is(
+        &self,
+        since: usize,
+        last_seen_broadcast: usize,
+    ) {
+        let guard = self.global.lock().unwrap();
+
+        let _ = self.condvar.wait_while(guard, |_| {
+            self.is_alive() &&
+                self.jobs_event_count() == since &&
+                !self.has_broadcast_since(last_seen_broadcast)
+        });
+
+        self.end_sleepy();
+    }
+}
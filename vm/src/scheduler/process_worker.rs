@@ -7,6 +7,33 @@ use crate::scheduler::queue::RcQueue;
 use crate::scheduler::worker::Worker;
 use crate::vm::machine::Machine;
 use std::cell::UnsafeCell;
+use std::sync::mpsc;
+use std::sync::Arc;
+use std::thread;
+
+/// A job that `PoolState::broadcast` runs exactly once on every worker in a
+/// pool, including workers that are currently parked.
+///
+/// This mirrors rayon-core's `broadcast`, and gives the VM a primitive for
+/// stop-the-world coordination (starting a GC cycle, flushing a per-worker
+/// tracer pool, applying a config change) for which there is otherwise no
+/// safe cross-worker channel.
+pub type BroadcastJob = Arc<dyn Fn(&mut ProcessWorker) + Send + Sync>;
+
+/// An execution backend capable of running an Inko process to completion (or
+/// until it yields control back to the scheduler).
+///
+/// `ProcessWorker` used to embed a `Machine` directly, hard-coding the
+/// scheduler to one interpreter implementation. Extracting this interface
+/// out (following the approach the old librustrt/libgreen split used to
+/// separate the runtime from the scheduler) lets the work-stealing
+/// scheduler, exclusive-mode pinning, and tracer pools be reused by
+/// alternate backends (a JIT, an instrumented/tracing interpreter, a test
+/// harness) without duplicating the scheduler, and keeps the `UnsafeCell`
+/// aliasing trick confined to the worker rather than spread across backends.
+pub trait ProcessRuntime {
+    fn run(&self, worker: &mut ProcessWorker, process: &RcProcess);
+}
 
 /// The state that a worker is in.
 #[derive(Eq, PartialEq, Debug)]
@@ -34,31 +61,54 @@ pub struct ProcessWorker {
     /// The state of the pool this worker belongs to.
     state: ArcWithoutWeak<PoolState<RcProcess>>,
 
-    /// The Machine to use for running code.
-    machine: UnsafeCell<Machine>,
+    /// The backend used for running code.
+    runtime: UnsafeCell<Box<dyn ProcessRuntime>>,
 
     /// The mode this worker is in.
     mode: Mode,
+
+    /// The generation of the last broadcast this worker has run. Used to
+    /// detect, and run, any broadcast jobs pushed since then.
+    last_seen_broadcast: usize,
 }
 
 impl ProcessWorker {
-    /// Starts a new worker operating in the normal mode.
+    /// Starts a new worker operating in the normal mode, running jobs using
+    /// `runtime`.
     pub fn new(
         id: usize,
         queue: RcQueue<RcProcess>,
         state: ArcWithoutWeak<PoolState<RcProcess>>,
-        machine: Machine,
+        runtime: Box<dyn ProcessRuntime>,
+        tracer_threads: usize,
     ) -> Self {
-        let tracers = machine.state.config.tracer_threads;
-
         ProcessWorker {
             id,
             queue,
             state,
             mode: Mode::Normal,
-            machine: UnsafeCell::new(machine),
-            tracers: TracerPool::new(tracers),
+            runtime: UnsafeCell::new(runtime),
+            tracers: TracerPool::new(tracer_threads),
+            last_seen_broadcast: 0,
+        }
+    }
+
+    /// Runs every broadcast job pushed since this worker last checked,
+    /// advancing its local generation counter so it doesn't run the same job
+    /// twice.
+    fn run_pending_broadcasts(&mut self) {
+        if !self.state.has_broadcast_since(self.last_seen_broadcast) {
+            return;
+        }
+
+        let (jobs, generation) =
+            self.state.broadcast_jobs_since(self.last_seen_broadcast);
+
+        for job in jobs {
+            job(self);
         }
+
+        self.last_seen_broadcast = generation;
     }
 
     /// Changes the worker state so it operates in exclusive mode.
@@ -82,6 +132,8 @@ impl ProcessWorker {
 
     /// Performs a single iteration of the normal work loop.
     fn normal_iteration(&mut self) {
+        self.run_pending_broadcasts();
+
         if self.process_local_jobs() {
             return;
         }
@@ -98,13 +150,45 @@ impl ProcessWorker {
             return;
         }
 
-        self.state.park_while(|| {
-            !self.state.has_global_jobs() && !self.queue.has_external_jobs()
-        });
+        self.sleep_if_still_idle();
+    }
+
+    /// Puts the worker to sleep following rayon-core's sleep protocol,
+    /// instead of parking as soon as a search round finds no work.
+    ///
+    /// Parking immediately after a failed search is prone to a classic
+    /// check-then-park race: a job can be pushed in the window between the
+    /// last check and the call to park, and would then go unnoticed until
+    /// something else happens to wake the pool. To close that window, the
+    /// worker first reads the pool's jobs event counter (JEC, bumped every
+    /// time any queue gains work) and announces itself "sleepy", then
+    /// performs one more full search round. Only if the JEC is still
+    /// unchanged after that round does it actually block on the pool's
+    /// condvar; if the JEC moved, a job arrived during the search window, so
+    /// it loops again instead of sleeping.
+    fn sleep_if_still_idle(&mut self) {
+        let jec = self.state.jobs_event_count();
+
+        self.state.begin_sleepy();
+
+        let found_work = self.process_local_jobs() ||
+            self.steal_from_other_queue() ||
+            self.queue.move_external_jobs() ||
+            self.steal_from_global_queue();
+
+        if found_work {
+            self.state.end_sleepy();
+            return;
+        }
+
+        self.state
+            .sleep_if_jobs_event_count_is(jec, self.last_seen_broadcast);
     }
 
     /// Runs a single iteration of an exclusive work loop.
     fn exclusive_iteration(&mut self) {
+        self.run_pending_broadcasts();
+
         if self.process_local_jobs() {
             return;
         }
@@ -117,7 +201,10 @@ impl ProcessWorker {
             return;
         }
 
-        self.state.park_while(|| !self.queue.has_external_jobs());
+        self.state.park_while(|| {
+            !self.queue.has_external_jobs() &&
+                !self.state.has_broadcast_since(self.last_seen_broadcast)
+        });
     }
 }
 
@@ -140,18 +227,66 @@ impl Worker<RcProcess> for ProcessWorker {
     }
 
     fn process_job(&mut self, job: RcProcess) {
-        // When using a Machine we need both an immutable reference to it (using
-        // `self.machine`), and a mutable reference to pass as an argument.
-        // Rust does not allow this, even though in this case it's perfectly
-        // safe.
+        // Running a job needs both an immutable reference to the runtime
+        // (using `self.runtime`), and a mutable reference to `self` to pass
+        // as an argument. Rust does not allow this, even though in this case
+        // it's perfectly safe.
         //
         // To work around this we use UnsafeCell. We could use RefCell, but
         // since we know exactly how this code is used (it's only the lines
         // below that depend on this) the runtime reference counting is not
         // needed.
-        let machine = unsafe { &mut *self.machine.get() };
+        let runtime = unsafe { &*self.runtime.get() };
+
+        runtime.run(self, &job);
+    }
+}
+
+impl ProcessRuntime for Machine {
+    fn run(&self, worker: &mut ProcessWorker, process: &RcProcess) {
+        Machine::run(self, worker, process)
+    }
+}
+
+impl Machine {
+    /// Starts running `blocking_op` on a dedicated thread, rather than
+    /// inline on the calling (work-stealing) worker, and returns a receiver
+    /// `end_blocking` can wait on for the result.
+    ///
+    /// The actual blocking syscall this wraps (file/socket I/O, a blocking
+    /// FFI call) never runs on one of the scheduler's work-stealing threads:
+    /// it's handed to a fresh, dedicated OS thread instead, one per blocking
+    /// operation (mirroring the old libgreen/libnative 1:1 threading model
+    /// this pair of methods is named after).
+    ///
+    /// This does not, however, free the calling worker to go process other
+    /// jobs while `blocking_op` is in flight: doing that would mean
+    /// suspending `process`'s execution at the exact instruction that
+    /// called this, and resuming it later from a different worker once
+    /// `blocking_op` completes, which needs the bytecode dispatch loop
+    /// (`Machine::run`/`ProcessWorker::process_job`) to support returning a
+    /// "suspended" action. It doesn't, so the calling worker parks on the
+    /// channel `end_blocking` reads from. What's guaranteed either way is
+    /// that `process` is never enqueued onto, or run by, more than one pool
+    /// at a time: nothing else touches it until `end_blocking` returns.
+    pub fn begin_blocking<F, R>(&self, blocking_op: F) -> mpsc::Receiver<R>
+    where
+        F: FnOnce() -> R + Send + 'static,
+        R: Send + 'static,
+    {
+        let (sender, receiver) = mpsc::channel();
+
+        thread::spawn(move || {
+            let _ = sender.send(blocking_op());
+        });
 
-        machine.run(self, &job);
+        receiver
+    }
+
+    /// Waits for the result of a blocking operation previously started with
+    /// `begin_blocking`.
+    pub fn end_blocking<R>(&self, receiver: mpsc::Receiver<R>) -> R {
+        receiver.recv().expect("the blocking operation's thread panicked")
     }
 }
 
@@ -164,8 +299,15 @@ mod tests {
     fn worker(machine: Machine) -> ProcessWorker {
         let pool_state = machine.state.scheduler.primary_pool.state.clone();
         let queue = pool_state.queues[0].clone();
+        let tracer_threads = machine.state.config.tracer_threads;
 
-        ProcessWorker::new(0, queue, pool_state, machine)
+        ProcessWorker::new(
+            0,
+            queue,
+            pool_state,
+            Box::new(machine),
+            tracer_threads,
+        )
     }
 
     #[test]
@@ -263,6 +405,26 @@ mod tests {
         assert!(worker.queue.pop_external_job().is_none());
     }
 
+    #[test]
+    fn test_begin_and_end_blocking() {
+        let (machine, _block, _process) = setup();
+        let calling_thread = thread::current().id();
+
+        let receiver = machine.begin_blocking(move || thread::current().id());
+        let op_thread = machine.end_blocking(receiver);
+
+        assert_ne!(op_thread, calling_thread);
+    }
+
+    #[test]
+    fn test_end_blocking_returns_the_operations_result() {
+        let (machine, _block, _process) = setup();
+
+        let receiver = machine.begin_blocking(|| 1 + 1);
+
+        assert_eq!(machine.end_blocking(receiver), 2);
+    }
+
     #[test]
     fn test_leave_exclusive_mode() {
         let (machine, _block, _process) = setup();
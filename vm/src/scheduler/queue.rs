@@ -0,0 +1,116 @@
+//! Per-worker queues used by the work-stealing scheduler.
+use crate::arc_without_weak::ArcWithoutWeak;
+use crate::scheduler::pool_state::PoolState;
+use std::collections::VecDeque;
+use std::sync::Mutex;
+
+struct Inner<T> {
+    /// Jobs this worker produced itself (e.g. by spawning a new process),
+    /// and is free to run in any order.
+    local: Mutex<VecDeque<T>>,
+
+    /// Jobs something else pinned to this particular worker, which must be
+    /// run by this worker and not stolen by another.
+    external: Mutex<VecDeque<T>>,
+
+    /// The pool this queue belongs to, used so `push_internal` and
+    /// `push_external` can bump the pool's jobs event counter the same way
+    /// `PoolState::push_global` does.
+    ///
+    /// This can't be supplied to `RcQueue::new`, since `Pool::new` builds
+    /// its queues before the `PoolState` that owns them exists; `Pool::new`
+    /// calls `bind` right after constructing it instead.
+    pool: Mutex<Option<ArcWithoutWeak<PoolState<T>>>>,
+}
+
+/// A worker's queue of pending jobs, reference counted so the pool's other
+/// workers can hold a handle to it (to steal from, or to push external jobs
+/// onto) alongside the owning worker.
+pub struct RcQueue<T> {
+    inner: ArcWithoutWeak<Inner<T>>,
+}
+
+impl<T> RcQueue<T> {
+    pub fn new() -> Self {
+        RcQueue {
+            inner: ArcWithoutWeak::new(Inner {
+                local: Mutex::new(VecDeque::new()),
+                external: Mutex::new(VecDeque::new()),
+                pool: Mutex::new(None),
+            }),
+        }
+    }
+
+    /// Binds this queue to the pool it belongs to. Called once by
+    /// `Pool::new`, right after the pool's `PoolState` is constructed.
+    pub fn bind(&self, state: ArcWithoutWeak<PoolState<T>>) {
+        *self.inner.pool.lock().unwrap() = Some(state);
+    }
+
+    /// Pushes a job onto this worker's local queue.
+    pub fn push_internal(&self, job: T) {
+        self.inner.local.lock().unwrap().push_back(job);
+        self.notify_jobs_event();
+    }
+
+    /// Pushes a job onto this worker's external queue, pinning it to this
+    /// worker so other workers won't steal it.
+    pub fn push_external(&self, job: T) {
+        self.inner.external.lock().unwrap().push_back(job);
+        self.notify_jobs_event();
+    }
+
+    /// Bumps the owning pool's jobs event counter, same as
+    /// `PoolState::push_global` does, so a worker about to park doesn't miss
+    /// a job that landed directly on a sibling's queue instead of the
+    /// pool's global queue.
+    fn notify_jobs_event(&self) {
+        if let Some(state) = self.inner.pool.lock().unwrap().as_ref() {
+            state.notify_jobs_event();
+        }
+    }
+
+    /// Pops the next local job, falling back to an external job if the local
+    /// queue is empty.
+    pub fn pop(&self) -> Option<T> {
+        self.inner
+            .local
+            .lock()
+            .unwrap()
+            .pop_front()
+            .or_else(|| self.pop_external_job())
+    }
+
+    /// Pops the next external job, if any.
+    pub fn pop_external_job(&self) -> Option<T> {
+        self.inner.external.lock().unwrap().pop_front()
+    }
+
+    pub fn has_local_jobs(&self) -> bool {
+        !self.inner.local.lock().unwrap().is_empty()
+    }
+
+    pub fn has_external_jobs(&self) -> bool {
+        !self.inner.external.lock().unwrap().is_empty()
+    }
+
+    /// Moves every pending external job onto the local queue, returning
+    /// whether any jobs were moved.
+    pub fn move_external_jobs(&self) -> bool {
+        let mut external = self.inner.external.lock().unwrap();
+
+        if external.is_empty() {
+            return false;
+        }
+
+        self.inner.local.lock().unwrap().extend(external.drain(..));
+
+        true
+    }
+}
+
+impl<T> Clone for RcQueue<T> {
+    fn clone(&self) -> Self {
+        RcQueue { inner: self.inner.clone() }
+    }
+}
@@ -79,52 +79,107 @@ pub fn run_block(_: &Machine,
 ///
 /// 1. The register to store the return value in.
 /// 2. The register containing the Block object to run.
+/// 3. A boolean (`0` or `1`) indicating whether the last given argument is a
+///    single array meant to be splatted (e.g. `block(*values)`), rather than
+///    a plain positional argument.
 ///
 /// Any extra arguments passed are passed as arguments to the CompiledCode
 /// object. If excessive arguments are given they are packed into the block's
 /// rest argument.
-pub fn run_block_with_rest(_: &Machine,
-                           _: &RcProcess,
+pub fn run_block_with_rest(machine: &Machine,
+                           process: &RcProcess,
                            _: &RcCompiledCode,
-                           _: &Instruction)
+                           instruction: &Instruction)
                            -> InstructionResult {
-    // TODO: implement
-    //let register = instruction.arg(0)?;
-    //let block_ptr = process.get_register(instruction.arg(1)?)?;
-    //let block_val = block_ptr.block_value()?;
-    //let has_rest = block_val.has_rest_argument();
-
-    // Unpack the last argument if it's a rest argument
-    //if rest_arg {
-    //if let Some(last_arg) = arguments.pop() {
-    //for value in last_arg.array_value()? {
-    //arguments.push(value.clone());
-    //}
-    //}
-    //}
-
-    // If the code object defines a rest argument we'll pack any excessive
-    // arguments into a single array.
-    //if block_val.has_rest_argument() && arguments.len() > tot_args {
-    //let rest_count = arguments.len() - tot_args;
-    //let mut rest = Vec::new();
-
-    //for obj in arguments[arguments.len() - rest_count..].iter() {
-    //rest.push(obj.clone());
-    //}
-
-    //arguments.truncate(tot_args);
-
-    //let rest_array = process.allocate(object_value::array(rest),
-    //machine.state.array_prototype.clone());
-
-    //arguments.push(rest_array);
-    //} else if block_val.has_rest_argument() && arguments.len() == 0 {
-    //let rest_array = process.allocate(object_value::array(Vec::new()),
-    //machine.state.array_prototype.clone());
-
-    //arguments.push(rest_array);
-    //}
+    process.advance_line(instruction.line);
+
+    let register = instruction.arg(0)?;
+    let block_ptr = process.get_register(instruction.arg(1)?)?;
+    let block_val = block_ptr.block_value()?;
+    let splat = instruction.arg(2)? == 1;
+
+    let arg_offset = 3;
+    let given_count = instruction.arguments.len() - arg_offset;
+    let tot_args = block_val.arguments();
+    let req_args = block_val.required_arguments();
+    let rest_arg = block_val.has_rest_argument();
+
+    let mut arguments = Vec::with_capacity(given_count);
+
+    for index in arg_offset..(arg_offset + given_count) {
+        let register = instruction.arg(index)?;
+
+        arguments.push(process.get_register(register)?);
+    }
+
+    // A splat call (`block(*values)`) passes its entire variadic portion as
+    // a single, already-built array in the last register. Unpack it into
+    // individual arguments before matching the call against the callee's
+    // arity, otherwise it would end up packed as one nested array inside
+    // the callee's own rest argument instead of being spread across it.
+    if splat {
+        if let Some(splat_array) = arguments.pop() {
+            for value in splat_array.array_value()? {
+                arguments.push(*value);
+            }
+        }
+    }
+
+    let arg_count = arguments.len();
+
+    if !rest_arg && arg_count > tot_args {
+        return Err(format!("{} accepts up to {} arguments, but {} arguments \
+                            were given",
+                           block_val.name(),
+                           tot_args,
+                           arg_count));
+    }
+
+    if arg_count < req_args {
+        return Err(format!("{} requires {} arguments, but {} arguments were \
+                            given",
+                           block_val.name(),
+                           req_args,
+                           arg_count));
+    }
+
+    // If the code object defines a rest argument we pack any arguments past
+    // `tot_args` into a single array, defaulting to an empty array when
+    // there are no surplus arguments to pack. Any optional fixed arguments
+    // that weren't given are padded with `nil` first, so the rest argument
+    // always ends up bound at the fixed `tot_args` local slot the compiler
+    // expects, regardless of how many of the fixed arguments were actually
+    // supplied.
+    if rest_arg {
+        let rest = if arguments.len() > tot_args {
+            arguments.split_off(tot_args)
+        } else {
+            while arguments.len() < tot_args {
+                arguments.push(machine.state.nil_object);
+            }
+
+            Vec::new()
+        };
+
+        let rest_ptr = process.allocate(object_value::array(rest),
+                                        machine.state.array_prototype.clone());
+
+        arguments.push(rest_ptr);
+    }
+
+    let context = ExecutionContext::with_binding(block_val.binding.clone(),
+                                                 block_val.code.clone(),
+                                                 Some(register));
+
+    {
+        let mut locals = context.binding.locals_mut();
+
+        for argument in arguments {
+            locals.push(argument);
+        }
+    }
+
+    process.push_context(context);
 
     Ok(Action::EnterContext)
 }
@@ -145,11 +200,14 @@ pub fn parse_file(machine: &Machine,
                   -> InstructionResult {
     let register = instruction.arg(0)?;
     let path_ptr = process.get_register(instruction.arg(1)?)?;
-    let path_str = path_ptr.string_value()?;
+    let path_str = path_ptr.string_value()?.to_string();
+    let registry = machine.file_registry.clone();
 
-    let code = write_lock!(machine.file_registry).get_or_set(path_str)
-        .map_err(|err| err.message())?;
+    let receiver = machine.begin_blocking(move || {
+        write_lock!(registry).get_or_set(&path_str).map_err(|err| err.message())
+    });
 
+    let code = machine.end_blocking(receiver)?;
     let block = Block::new(code.clone(), Binding::new());
 
     let block_ptr = process.allocate(object_value::block(block),
@@ -336,4 +394,231 @@ mod tests {
                     machine.state.false_object);
         }
     }
+
+    mod run_block_with_rest {
+        use super::*;
+
+        #[test]
+        fn test_without_arguments() {
+            let (machine, code, process) = setup();
+
+            let block = Block::new(code.clone(), Binding::new());
+
+            let block_ptr =
+                process.allocate_without_prototype(object_value::block(block));
+
+            process.set_register(0, block_ptr);
+            process.set_register(1, machine.state.false_object);
+
+            let instruction = new_instruction(InstructionType::RunBlockWithRest,
+                                              vec![2, 0, 0]);
+
+            let result = run_block_with_rest(&machine, &process, &code, &instruction);
+
+            assert!(result.is_ok());
+            assert!(process.binding().locals().is_empty());
+        }
+
+        #[test]
+        fn test_with_too_many_arguments() {
+            let (machine, code, process) = setup();
+
+            let block = Block::new(code.clone(), Binding::new());
+
+            let block_ptr =
+                process.allocate_without_prototype(object_value::block(block));
+
+            process.set_register(0, block_ptr);
+            process.set_register(1, machine.state.true_object);
+            process.set_register(2, machine.state.false_object);
+
+            let instruction = new_instruction(InstructionType::RunBlockWithRest,
+                                              vec![3, 0, 0, 1, 2]);
+
+            let result = run_block_with_rest(&machine, &process, &code, &instruction);
+
+            assert!(result.is_err());
+        }
+
+        #[test]
+        fn test_with_not_enough_arguments() {
+            let (machine, code, process) = setup();
+
+            arc_mut(&code).arguments = 2;
+            arc_mut(&code).required_arguments = 2;
+
+            let block = Block::new(code.clone(), Binding::new());
+
+            let block_ptr =
+                process.allocate_without_prototype(object_value::block(block));
+
+            process.set_register(0, block_ptr);
+            process.set_register(1, machine.state.true_object);
+            process.set_register(2, machine.state.false_object);
+
+            let instruction = new_instruction(InstructionType::RunBlockWithRest,
+                                              vec![3, 0, 0, 1]);
+
+            let result = run_block_with_rest(&machine, &process, &code, &instruction);
+
+            assert!(result.is_err());
+        }
+
+        #[test]
+        fn test_with_surplus_arguments_packed_into_rest() {
+            let (machine, code, process) = setup();
+
+            arc_mut(&code).arguments = 1;
+            arc_mut(&code).rest_argument = true;
+
+            let block = Block::new(code.clone(), Binding::new());
+
+            let block_ptr =
+                process.allocate_without_prototype(object_value::block(block));
+
+            process.set_register(0, block_ptr);
+            process.set_register(1, machine.state.true_object);
+            process.set_register(2, machine.state.false_object);
+            process.set_register(3, machine.state.true_object);
+
+            let instruction = new_instruction(InstructionType::RunBlockWithRest,
+                                              vec![4, 0, 0, 1, 2, 3]);
+
+            let result = run_block_with_rest(&machine, &process, &code, &instruction);
+
+            assert!(result.is_ok());
+            assert_eq!(process.binding().locals().len(), 2);
+
+            assert!(process.binding().get_local(0).unwrap() ==
+                    machine.state.true_object);
+
+            let rest = process.binding()
+                .get_local(1)
+                .unwrap()
+                .array_value()
+                .unwrap()
+                .clone();
+
+            assert_eq!(rest.len(), 2);
+            assert!(rest[0] == machine.state.false_object);
+            assert!(rest[1] == machine.state.true_object);
+        }
+
+        #[test]
+        fn test_with_rest_argument_and_no_surplus() {
+            let (machine, code, process) = setup();
+
+            arc_mut(&code).arguments = 2;
+            arc_mut(&code).rest_argument = true;
+
+            let block = Block::new(code.clone(), Binding::new());
+
+            let block_ptr =
+                process.allocate_without_prototype(object_value::block(block));
+
+            process.set_register(0, block_ptr);
+            process.set_register(1, machine.state.true_object);
+            process.set_register(2, machine.state.false_object);
+
+            let instruction = new_instruction(InstructionType::RunBlockWithRest,
+                                              vec![3, 0, 0, 1, 2]);
+
+            let result = run_block_with_rest(&machine, &process, &code, &instruction);
+
+            assert!(result.is_ok());
+            assert_eq!(process.binding().locals().len(), 3);
+
+            let rest = process.binding()
+                .get_local(2)
+                .unwrap()
+                .array_value()
+                .unwrap()
+                .clone();
+
+            assert!(rest.is_empty());
+        }
+
+        #[test]
+        fn test_with_rest_argument_and_optional_fixed_argument_missing() {
+            let (machine, code, process) = setup();
+
+            arc_mut(&code).arguments = 2;
+            arc_mut(&code).required_arguments = 1;
+            arc_mut(&code).rest_argument = true;
+
+            let block = Block::new(code.clone(), Binding::new());
+
+            let block_ptr =
+                process.allocate_without_prototype(object_value::block(block));
+
+            process.set_register(0, block_ptr);
+            process.set_register(1, machine.state.true_object);
+
+            let instruction = new_instruction(InstructionType::RunBlockWithRest,
+                                              vec![2, 0, 0, 1]);
+
+            let result = run_block_with_rest(&machine, &process, &code, &instruction);
+
+            assert!(result.is_ok());
+            assert_eq!(process.binding().locals().len(), 3);
+
+            assert!(process.binding().get_local(0).unwrap() ==
+                    machine.state.true_object);
+
+            assert!(process.binding().get_local(1).unwrap() ==
+                    machine.state.nil_object);
+
+            let rest = process.binding()
+                .get_local(2)
+                .unwrap()
+                .array_value()
+                .unwrap()
+                .clone();
+
+            assert!(rest.is_empty());
+        }
+
+        #[test]
+        fn test_with_splat_argument() {
+            let (machine, code, process) = setup();
+
+            arc_mut(&code).arguments = 1;
+            arc_mut(&code).rest_argument = true;
+
+            let block = Block::new(code.clone(), Binding::new());
+
+            let block_ptr =
+                process.allocate_without_prototype(object_value::block(block));
+
+            let splat =
+                process.allocate_without_prototype(object_value::array(vec![machine.state.false_object,
+                                                                       machine.state.true_object]));
+
+            process.set_register(0, block_ptr);
+            process.set_register(1, machine.state.true_object);
+            process.set_register(2, splat);
+
+            let instruction = new_instruction(InstructionType::RunBlockWithRest,
+                                              vec![3, 0, 1, 1, 2]);
+
+            let result = run_block_with_rest(&machine, &process, &code, &instruction);
+
+            assert!(result.is_ok());
+            assert_eq!(process.binding().locals().len(), 2);
+
+            assert!(process.binding().get_local(0).unwrap() ==
+                    machine.state.true_object);
+
+            let rest = process.binding()
+                .get_local(1)
+                .unwrap()
+                .array_value()
+                .unwrap()
+                .clone();
+
+            assert_eq!(rest.len(), 2);
+            assert!(rest[0] == machine.state.false_object);
+            assert!(rest[1] == machine.state.true_object);
+        }
+    }
 }